@@ -0,0 +1,122 @@
+//! Content-addressed cache for downloaded release tarballs.
+//!
+//! Blobs are stored under `~/.cache/mkdeb/<algo>/<hash-prefix>/<hash>`,
+//! keyed by the Subresource-Integrity string (`sha512-<base64>` or
+//! `sha256-<base64>`) computed over the raw bytes as they stream to disk.
+//! This mirrors the cacache/integrity approach used by npm's prefetcher:
+//! a build that names an `integrity` string skips the tarball download
+//! once the blob is on disk. Resolving the release still queries the
+//! GitHub API unless `--locked` takes it from `mkdeb.lock`.
+
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha512};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Streaming hasher used by `download_with_progress` so integrity is
+/// computed in the same pass that writes bytes to disk.
+pub enum StreamingHasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl StreamingHasher {
+    /// Create a hasher for `algo` ("sha256" or "sha512"). Defaults to
+    /// sha512 for an unrecognized or absent algorithm.
+    pub fn new(algo: &str) -> Self {
+        match algo {
+            "sha256" => StreamingHasher::Sha256(Sha256::new()),
+            _ => StreamingHasher::Sha512(Sha512::new()),
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        match self {
+            StreamingHasher::Sha256(h) => h.update(chunk),
+            StreamingHasher::Sha512(h) => h.update(chunk),
+        }
+    }
+
+    /// Finish hashing and render the result as a `sha512-<base64>` (or
+    /// `sha256-...`) integrity string.
+    pub fn finish(self) -> String {
+        match self {
+            StreamingHasher::Sha256(h) => encode("sha256", &h.finalize()),
+            StreamingHasher::Sha512(h) => encode("sha512", &h.finalize()),
+        }
+    }
+}
+
+fn encode(algo: &str, digest: &[u8]) -> String {
+    format!(
+        "{}-{}",
+        algo,
+        base64::engine::general_purpose::STANDARD.encode(digest)
+    )
+}
+
+/// Parse the algorithm prefix (`sha512`, `sha256`, ...) off an integrity
+/// string, e.g. `"sha512-abc..."` -> `"sha512"`.
+pub fn algo_of(integrity: &str) -> &str {
+    integrity.split_once('-').map_or(integrity, |(algo, _)| algo)
+}
+
+fn cache_root() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("mkdeb")
+}
+
+/// Turn an integrity string into its on-disk path, without checking that
+/// it exists. The base64 hash is filesystem-escaped (`/` -> `_`) and used
+/// both as the leaf name and, via its first two characters, as a
+/// fan-out directory so no single directory gets too many entries.
+fn blob_path(integrity: &str) -> Option<PathBuf> {
+    let (algo, hash) = integrity.split_once('-')?;
+    let hash = hash.replace('/', "_");
+    if hash.len() < 2 {
+        return None;
+    }
+    let prefix = &hash[..2];
+    Some(cache_root().join(algo).join(prefix).join(hash))
+}
+
+/// Look up `integrity` in the cache, returning the blob path if present.
+pub fn lookup(integrity: &str) -> Option<PathBuf> {
+    let path = blob_path(integrity)?;
+    path.is_file().then_some(path)
+}
+
+/// Copy `src` into the cache keyed by `integrity`, creating parent
+/// directories as needed. Returns the final cached path.
+pub fn store(integrity: &str, src: &Path) -> io::Result<PathBuf> {
+    let dest = blob_path(integrity)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "malformed integrity string"))?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(src, &dest)?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_path_fans_out_by_hash_prefix() {
+        let path = blob_path("sha512-ab/cd==").unwrap();
+        let mut components = path.components().rev();
+        assert_eq!(components.next().unwrap().as_os_str(), "ab_cd==");
+        assert_eq!(components.next().unwrap().as_os_str(), "ab");
+        assert_eq!(components.next().unwrap().as_os_str(), "sha512");
+    }
+
+    #[test]
+    fn blob_path_rejects_malformed_integrity() {
+        assert!(blob_path("nodashatall").is_none());
+        assert!(blob_path("sha512-").is_none());
+        assert!(blob_path("sha512-a").is_none());
+    }
+}
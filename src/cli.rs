@@ -1,7 +1,7 @@
 use clap::{ArgAction, ColorChoice, Parser};
 
 /// mkdeb: GitHub → build → .deb
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     name = "mkdeb",
     version,
@@ -48,4 +48,24 @@ pub struct CliArgs {
     /// Use specified path instead of a temporary directory for building
     #[arg(long)]
     pub build_root: Option<String>,
+
+    /// Bypass the content-addressed download cache entirely
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub no_cache: bool,
+
+    /// Number of packages to build concurrently (defaults to the number of CPUs)
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Resolve exclusively from mkdeb.lock, without contacting the GitHub API
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub locked: bool,
+
+    /// Re-resolve locked packages against the GitHub API and refresh mkdeb.lock
+    #[arg(long = "update", visible_alias = "upgrade", action = ArgAction::SetTrue)]
+    pub update: bool,
+
+    /// GitHub API token (also read from GITHUB_TOKEN/MKDEB_GITHUB_TOKEN)
+    #[arg(long)]
+    pub token: Option<String>,
 }
@@ -1,5 +1,7 @@
 use log::{debug, error, info};
+use std::fs;
 use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Command;
 
@@ -27,6 +29,12 @@ pub struct ControlMetadata<'a> {
     pub description: Option<&'a str>,
     pub deps: Option<&'a str>,
     pub build_deps: Option<&'a str>,
+    /// Additional RFC822 control fields (`Section`, `Priority`, `Homepage`,
+    /// `Provides`, `Conflicts`, `Replaces`, ...) written in order after the
+    /// fields above. Keeping these as an ordered list rather than named
+    /// struct fields lets callers add fields without `write_control`
+    /// needing to know about each one.
+    pub extra_fields: Vec<(&'a str, &'a str)>,
 }
 
 pub fn write_control(meta: &ControlMetadata, control_dir: &Path) {
@@ -51,6 +59,9 @@ pub fn write_control(meta: &ControlMetadata, control_dir: &Path) {
     if let Some(bdep_str) = meta.build_deps {
         control.push_str(&format!("Build-Depends: {}\n", bdep_str));
     }
+    for (field, value) in &meta.extra_fields {
+        control.push_str(&format!("{}: {}\n", field, value));
+    }
 
     let mut file =
         std::fs::File::create(control_dir.join("control")).expect("Failed to open control file");
@@ -58,6 +69,53 @@ pub fn write_control(meta: &ControlMetadata, control_dir: &Path) {
     debug!("control file:\n{}", control);
 }
 
+/// Maintainer script bodies for `DEBIAN/{preinst,postinst,prerm,postrm}`.
+///
+/// Unlike `run_command`'s `{destdir}`, these bodies are run verbatim with
+/// no interpolation: `run_command` executes at *build* time, in the
+/// staging `destdir` under a temp directory mkdeb controls, but maintainer
+/// scripts execute at *install* time on the target machine, where that
+/// staging path no longer exists. A script needing the installed layout
+/// should use paths relative to `/` (what dpkg installed the package
+/// under), the same as any hand-written `DEBIAN/postinst`.
+pub struct MaintainerScripts<'a> {
+    pub preinst: Option<&'a str>,
+    pub postinst: Option<&'a str>,
+    pub prerm: Option<&'a str>,
+    pub postrm: Option<&'a str>,
+}
+
+/// Write each script in `scripts` that is set to `control_dir`, setting mode
+/// 0755 as dpkg requires. Bodies are written as-is; see `MaintainerScripts`
+/// for why there's no `{destdir}` interpolation here.
+pub fn write_maintainer_scripts(scripts: &MaintainerScripts, control_dir: &Path) {
+    let entries: [(&str, Option<&str>); 4] = [
+        ("preinst", scripts.preinst),
+        ("postinst", scripts.postinst),
+        ("prerm", scripts.prerm),
+        ("postrm", scripts.postrm),
+    ];
+
+    for (name, body) in entries {
+        let Some(body) = body else { continue };
+        let script = format!("#!/bin/sh\nset -e\n{}\n", body);
+        let path = control_dir.join(name);
+        fs::write(&path, script).expect("Failed to write maintainer script");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .expect("Failed to set maintainer script permissions");
+    }
+}
+
+/// Write `DEBIAN/conffiles`, one path per line, so dpkg preserves local
+/// edits to these files across upgrades. No-op if `conffiles` is empty.
+pub fn write_conffiles(conffiles: &[String], control_dir: &Path) {
+    if conffiles.is_empty() {
+        return;
+    }
+    let content = format!("{}\n", conffiles.join("\n"));
+    fs::write(control_dir.join("conffiles"), content).expect("Failed to write conffiles");
+}
+
 pub fn build_package(destdir: &Path, output_path: &Path) {
     let mut cmd = Command::new("dpkg-deb");
     cmd.arg("--build")
@@ -1,11 +1,26 @@
-use crate::debug;
+use crate::{debug, error};
+use semver::{Op, Version, VersionReq};
 
 pub struct GithubRelease {
     pub tag: String,
     pub tarball_url: String,
     pub version: String,
+    /// The commit SHA `tag` points to, recorded in the lockfile so a
+    /// `--locked` rebuild pins the exact commit, not just the tag name.
+    pub commit: String,
 }
 
+const USER_AGENT: &str = "mkdeb/0.1 (https://github.com/youruser/mkdeb)";
+/// Safety cap on how many `Link: rel="next"` pages a "pick the highest
+/// matching version" resolve will follow, so a repo with thousands of
+/// unrelated releases can't make a build hang paging through all of them.
+const MAX_PAGES: u32 = 10;
+/// Cap for resolving a single specifically-named pin (an exact semver
+/// version, or a non-semver tag via `find_exact_tag`): since the whole
+/// point is to find one named tag rather than compare many, it's worth
+/// paging much further before giving up.
+const MAX_PAGES_EXACT_PIN: u32 = 100;
+
 fn extract_deb_version(tag: &str, published_at: Option<&str>) -> String {
     if tag.starts_with('v') && tag.chars().nth(1).is_some_and(|c| c.is_ascii_digit()) {
         tag.trim_start_matches('v').to_string()
@@ -18,72 +33,487 @@ fn extract_deb_version(tag: &str, published_at: Option<&str>) -> String {
     }
 }
 
-pub async fn find_release(repo: &str, version: Option<&str>) -> Option<GithubRelease> {
-    let client = reqwest::Client::new();
-    let url = format!("https://api.github.com/repos/{}/releases", repo);
-    debug!("Fetching {}...", url);
-    let response = client
-        .get(&url)
-        .header(
-            "User-Agent",
-            "mkdeb/0.1 (https://github.com/youruser/mkdeb)",
-        )
-        .send()
-        .await
-        .ok()?;
-
-    let releases: serde_json::Value = response.json().await.ok()?;
-    let releases = releases.as_array()?;
-
-    for release in releases {
-        let tag = release.get("tag_name")?.as_str()?;
-        let published_at = release.get("published_at").and_then(|d| d.as_str());
-        let rel_ver = extract_deb_version(tag, published_at);
-        debug!(
-            "considering tag: {} rel_ver: {} published: {:#?}",
-            tag, rel_ver, published_at
-        );
-        if version.is_none() || version == Some(rel_ver.as_str()) {
-            let tarball_url = release.get("tarball_url")?.as_str()?.to_string();
+/// Parse a release/tag name into a `semver::Version`, stripping a leading
+/// `v` the way cargo does (`v2.0.0-rc1` -> `2.0.0-rc1`). Returns `None` for
+/// tags that aren't valid semver (e.g. date-based version schemes), which
+/// fall back to `extract_deb_version`'s plain string handling instead.
+fn parse_tag_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
 
-            return Some(GithubRelease {
+/// Does `req` itself opt into prereleases, i.e. does one of its
+/// comparators carry a prerelease segment (as `VersionReq::matches` only
+/// matches a prerelease version against a comparator for the same
+/// major.minor.patch that also names a prerelease)?
+fn req_wants_prerelease(req: &VersionReq) -> bool {
+    req.comparators.iter().any(|c| !c.pre.is_empty())
+}
+
+/// A release/tag candidate with its parsed semver, kept alongside the raw
+/// JSON so we can still pull `tarball_url` out of whichever one wins.
+struct Candidate<'a> {
+    tag: String,
+    version: Version,
+    json: &'a serde_json::Value,
+}
+
+/// Pick the highest version satisfying `version_req` (a semver constraint
+/// such as `">=1.2, <2"` or `"^3.1"`, or `None` for "latest") out of
+/// `items`, skipping prereleases unless `allow_prerelease` is set or the
+/// constraint itself names one.
+fn select_best<'a>(
+    items: &'a [serde_json::Value],
+    version_req: &Option<VersionReq>,
+    allow_prerelease: bool,
+) -> Option<(&'a serde_json::Value, String, String)> {
+    let mut candidates: Vec<Candidate> = items
+        .iter()
+        .filter_map(|item| {
+            let tag = item.get("tag_name").or_else(|| item.get("name"))?.as_str()?;
+            let version = parse_tag_version(tag)?;
+            Some(Candidate {
                 tag: tag.to_string(),
-                tarball_url,
-                version: rel_ver,
-            });
+                version,
+                json: item,
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| b.version.cmp(&a.version));
+
+    let wants_pre = allow_prerelease || version_req.as_ref().is_some_and(req_wants_prerelease);
+
+    for candidate in &candidates {
+        if !candidate.version.pre.is_empty() && !wants_pre {
+            continue;
+        }
+        let matches = match version_req {
+            // `VersionReq::matches` only matches a prerelease version
+            // against a comparator that names a prerelease at the same
+            // major.minor.patch, so `"^2"` can never match `2.1.0-rc1` on
+            // its own merits — the caller already opted into prereleases
+            // above, so compare the release portion instead, the same way
+            // an explicit `allow_prerelease` is supposed to work. A
+            // comparator that names this very prerelease (`"=1.2.3-rc1"`)
+            // still matches it directly.
+            Some(req) if !candidate.version.pre.is_empty() => {
+                let release_only =
+                    Version::new(candidate.version.major, candidate.version.minor, candidate.version.patch);
+                req.matches(&candidate.version) || req.matches(&release_only)
+            }
+            Some(req) => req.matches(&candidate.version),
+            None => true,
+        };
+        if matches {
+            return Some((candidate.json, candidate.tag.clone(), candidate.version.to_string()));
         }
     }
 
-    debug!("no releases found, trying with tags");
+    None
+}
+
+/// Whether `req` pins a single exact version (`"=1.2.3"`), the only case
+/// where it's safe to stop paging as soon as one page satisfies it — any
+/// other constraint (`"^1"`, `">=1, <2"`, "latest") needs every page seen
+/// before picking the highest match, since GitHub doesn't order
+/// releases/tags by semver. A partial `=` constraint like `"=1"` or
+/// `"=1.2"` is actually a *range* (`>=1.0.0,<2.0.0` / `>=1.2.0,<1.3.0`), so
+/// it must take the accumulate-all-pages path too — only a comparator that
+/// names major, minor, *and* patch pins one specific version.
+fn is_exact_pin(req: &VersionReq) -> bool {
+    matches!(req.comparators.as_slice(), [c] if c.op == Op::Exact && c.minor.is_some() && c.patch.is_some())
+}
+
+fn commit_sha_from_tags(tags: &[serde_json::Value], tag: &str) -> Option<String> {
+    tags.iter()
+        .find(|t| t.get("name").and_then(|n| n.as_str()) == Some(tag))
+        .and_then(|t| t.get("commit")?.get("sha")?.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Extract the `rel="next"` target from a GitHub `Link` response header,
+/// e.g. `<https://api.github.com/...&page=2>; rel="next", <...>; rel="last"`.
+fn parse_next_link(link_header: &str) -> Option<String> {
+    link_header.split(',').find_map(|part| {
+        let mut segments = part.split(';');
+        let url_part = segments.next()?.trim();
+        let is_next = segments.any(|s| s.trim() == r#"rel="next""#);
+        is_next.then(|| url_part.trim_matches(|c| c == '<' || c == '>').to_string())
+    })
+}
+
+fn authed_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client.get(url).header("User-Agent", USER_AGENT);
+    match token {
+        Some(t) => request.header("Authorization", format!("Bearer {}", t)),
+        None => request,
+    }
+}
+
+/// `true` if `response` is a GitHub rate-limit rejection (403 with
+/// `X-RateLimit-Remaining: 0`); logs a clear message naming the reset time.
+fn check_rate_limited(response: &reqwest::Response) -> bool {
+    if response.status() != reqwest::StatusCode::FORBIDDEN {
+        return false;
+    }
+    let remaining = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok());
+    if remaining != Some("0") {
+        return false;
+    }
+    let reset = response
+        .headers()
+        .get("x-ratelimit-reset")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("unknown");
+    error!(
+        "GitHub API rate limit exceeded (resets at unix time {}); set GITHUB_TOKEN, MKDEB_GITHUB_TOKEN, or --token",
+        reset
+    );
+    true
+}
+
+/// Fetch `start_url` and, as needed, successive `Link: rel="next"` pages
+/// (up to `MAX_PAGES`, or `MAX_PAGES_EXACT_PIN` for a single-version pin),
+/// accumulating every page's array. GitHub orders `/releases` by publish
+/// date and `/tags` arbitrarily, neither of which is semver order, so a
+/// higher satisfying version can be sitting on a later page than a lower
+/// one that happens to match first — we only early-exit once a page
+/// satisfies an exact version pin, where there's nothing a later page
+/// could do but duplicate it. Any other constraint accumulates every page
+/// before `select_best` picks the highest match, and logs an `error!` if
+/// the cap is hit before a match is found, so "not found" is distinguishable
+/// from "gave up". Returns the matched item (cloned), its tag and version,
+/// and every item seen so far.
+async fn paginate_and_select(
+    client: &reqwest::Client,
+    token: Option<&str>,
+    start_url: &str,
+    version_req: &Option<VersionReq>,
+    allow_prerelease: bool,
+) -> Option<(serde_json::Value, String, String, Vec<serde_json::Value>)> {
+    let mut items: Vec<serde_json::Value> = Vec::new();
+    let mut next_url = Some(start_url.to_string());
+    let mut page = 0;
+    let exact_pin = version_req.as_ref().is_some_and(is_exact_pin);
+    let max_pages = if exact_pin { MAX_PAGES_EXACT_PIN } else { MAX_PAGES };
+
+    while let Some(url) = next_url.take() {
+        page += 1;
+        debug!("Fetching {} (page {})...", url, page);
+
+        let response = authed_get(client, &url, token).send().await.ok()?;
+
+        if check_rate_limited(&response) {
+            return None;
+        }
+
+        next_url = response
+            .headers()
+            .get("link")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_next_link);
+
+        let page_json: serde_json::Value = response.json().await.ok()?;
+        items.extend(page_json.as_array()?.iter().cloned());
+
+        if exact_pin {
+            if let Some((item, tag, version)) = select_best(&items, version_req, allow_prerelease) {
+                return Some((item.clone(), tag, version, items));
+            }
+        }
+
+        if next_url.is_some() && page >= max_pages {
+            if select_best(&items, version_req, allow_prerelease).is_none() {
+                error!(
+                    "gave up paging {} after {} pages without a satisfying match; \
+                     the real answer may be on a later page",
+                    start_url, page
+                );
+            }
+            break;
+        }
+    }
+
+    let (item, tag, version) = select_best(&items, version_req, allow_prerelease)?;
+    Some((item.clone(), tag, version, items))
+}
+
+/// Look up the commit SHA `tag` points to, reusing an already-fetched
+/// `/tags` page when the caller has one, otherwise fetching it.
+async fn resolve_commit_sha(
+    client: &reqwest::Client,
+    repo: &str,
+    tag: &str,
+    token: Option<&str>,
+    tags: Option<&[serde_json::Value]>,
+) -> String {
+    if let Some(tags) = tags {
+        if let Some(sha) = commit_sha_from_tags(tags, tag) {
+            return sha;
+        }
+    }
 
     let tags_url = format!("https://api.github.com/repos/{}/tags", repo);
-    debug!("Fetching {}...", tags_url);
-    let tag_response = client
-        .get(&tags_url)
-        .header(
-            "User-Agent",
-            "mkdeb/0.1 (https://github.com/youruser/mkdeb)",
-        )
-        .send()
-        .await
-        .ok()?;
-
-    let tags: serde_json::Value = tag_response.json().await.ok()?;
-    let tags = tags.as_array()?;
-
-    for tag_obj in tags {
-        let tag_name = tag_obj.get("name")?.as_str()?;
-        let rel_ver = extract_deb_version(tag_name, None);
-        if version.is_none() || version == Some(rel_ver.as_str()) {
-            let tarball_url = format!("https://api.github.com/repos/{}/tarball/{}", repo, tag_name);
+    let response = authed_get(client, &tags_url, token).send().await.ok();
+
+    let fetched: Option<serde_json::Value> = match response {
+        Some(r) => r.json().await.ok(),
+        None => None,
+    };
+
+    fetched
+        .as_ref()
+        .and_then(|v| v.as_array())
+        .and_then(|arr| commit_sha_from_tags(arr, tag))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Resolve `version` as an exact, non-semver pin (date tags, `20210101`,
+/// ...) the way the original string-matching resolver did, for repos whose
+/// tags don't parse as semver at all. Walks `Link: rel="next"` pages up to
+/// `MAX_PAGES_EXACT_PIN` — since this is always resolving one specifically
+/// named tag, it's worth paging much further than the "pick the best among
+/// many" resolver does before giving up.
+async fn find_exact_tag(repo: &str, version: &str, token: Option<&str>) -> Option<GithubRelease> {
+    let client = reqwest::Client::new();
+
+    let releases_url = format!("https://api.github.com/repos/{}/releases", repo);
+    let mut next_url = Some(releases_url);
+    let mut page = 0;
 
+    while let Some(url) = next_url.take() {
+        page += 1;
+        debug!("Fetching {} (page {})...", url, page);
+
+        let response = authed_get(&client, &url, token).send().await.ok()?;
+        if check_rate_limited(&response) {
+            return None;
+        }
+
+        next_url = response
+            .headers()
+            .get("link")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_next_link);
+
+        let releases: serde_json::Value = response.json().await.ok()?;
+        if let Some(releases) = releases.as_array() {
+            for release in releases {
+                let tag = release.get("tag_name")?.as_str()?;
+                let published_at = release.get("published_at").and_then(|d| d.as_str());
+                let rel_ver = extract_deb_version(tag, published_at);
+                if rel_ver == version {
+                    let tarball_url = release.get("tarball_url")?.as_str()?.to_string();
+                    let commit = resolve_commit_sha(&client, repo, tag, token, None).await;
+                    return Some(GithubRelease {
+                        tag: tag.to_string(),
+                        tarball_url,
+                        version: rel_ver,
+                        commit,
+                    });
+                }
+            }
+        }
+
+        if next_url.is_some() && page >= MAX_PAGES_EXACT_PIN {
+            error!(
+                "gave up looking for release tag {:?} in {} after {} pages",
+                version, repo, page
+            );
+            break;
+        }
+    }
+
+    debug!("no matching release found, trying with tags");
+
+    let tags_url = format!("https://api.github.com/repos/{}/tags", repo);
+    let mut next_url = Some(tags_url);
+    let mut page = 0;
+
+    while let Some(url) = next_url.take() {
+        page += 1;
+        debug!("Fetching {} (page {})...", url, page);
+
+        let response = authed_get(&client, &url, token).send().await.ok()?;
+        if check_rate_limited(&response) {
+            return None;
+        }
+
+        next_url = response
+            .headers()
+            .get("link")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_next_link);
+
+        let tags: serde_json::Value = response.json().await.ok()?;
+        let tags = tags.as_array()?;
+
+        for tag_obj in tags {
+            let tag_name = tag_obj.get("name")?.as_str()?;
+            let rel_ver = extract_deb_version(tag_name, None);
+            if rel_ver == version {
+                let tarball_url = format!("https://api.github.com/repos/{}/tarball/{}", repo, tag_name);
+                let commit = resolve_commit_sha(&client, repo, tag_name, token, Some(tags)).await;
+                return Some(GithubRelease {
+                    tag: tag_name.to_string(),
+                    tarball_url,
+                    version: rel_ver,
+                    commit,
+                });
+            }
+        }
+
+        if next_url.is_some() && page >= MAX_PAGES_EXACT_PIN {
+            error!(
+                "gave up looking for tag {:?} in {} after {} pages",
+                version, repo, page
+            );
+            break;
+        }
+    }
+
+    None
+}
+
+pub async fn find_release(
+    repo: &str,
+    version: Option<&str>,
+    allow_prerelease: bool,
+    token: Option<&str>,
+) -> Option<GithubRelease> {
+    let version_req = match version {
+        Some(s) => match VersionReq::parse(s) {
+            Ok(req) => Some(req),
+            Err(e) => {
+                // Not a semver constraint (date tags, `2021-01-01`, ...): the
+                // caller pinned something specific, so fall back to the old
+                // exact string-match resolver instead of treating this as
+                // "no constraint" and silently resolving latest below.
+                debug!("invalid version constraint {:?} for {}: {}", s, repo, e);
+                return find_exact_tag(repo, s, token).await;
+            }
+        },
+        None => None,
+    };
+
+    let client = reqwest::Client::new();
+    let releases_url = format!("https://api.github.com/repos/{}/releases", repo);
+
+    if let Some((release, tag, version)) =
+        paginate_and_select(&client, token, &releases_url, &version_req, allow_prerelease).await
+    {
+        debug!("selected tag: {} version: {}", tag, version);
+        let tarball_url = release.get("tarball_url")?.as_str()?.to_string();
+        let commit = resolve_commit_sha(&client, repo, &tag, token, None).await;
+        return Some(GithubRelease {
+            tag,
+            tarball_url,
+            version,
+            commit,
+        });
+    }
+
+    debug!("no matching release found, trying with tags");
+
+    let tags_url = format!("https://api.github.com/repos/{}/tags", repo);
+    if let Some((_, tag, version)) =
+        paginate_and_select(&client, token, &tags_url, &version_req, allow_prerelease).await
+    {
+        let tarball_url = format!("https://api.github.com/repos/{}/tarball/{}", repo, tag);
+        let commit = resolve_commit_sha(&client, repo, &tag, token, None).await;
+        return Some(GithubRelease {
+            tag,
+            tarball_url,
+            version,
+            commit,
+        });
+    }
+
+    // Neither releases nor tags had a semver-parseable match; fall back to
+    // the old plain-string/date-based scheme for repos that don't tag
+    // releases with semver (only usable for "give me the latest").
+    if version_req.is_none() {
+        let response = authed_get(&client, &tags_url, token).send().await.ok()?;
+        if check_rate_limited(&response) {
+            return None;
+        }
+        let tags: serde_json::Value = response.json().await.ok()?;
+        let tags = tags.as_array()?;
+
+        if let Some(tag_obj) = tags.first() {
+            let tag_name = tag_obj.get("name")?.as_str()?;
+            let rel_ver = extract_deb_version(tag_name, None);
+            let tarball_url = format!("https://api.github.com/repos/{}/tarball/{}", repo, tag_name);
+            let commit = resolve_commit_sha(&client, repo, tag_name, token, Some(tags)).await;
             return Some(GithubRelease {
                 tag: tag_name.to_string(),
                 tarball_url,
                 version: rel_ver,
+                commit,
             });
         }
     }
 
+    // An all-digit date tag such as `20210101` parses as the semver
+    // constraint `^20210101` but never as a tag `Version`, so a pin that
+    // found no semver match gets the exact string match as well.
+    if let Some(s) = version {
+        return find_exact_tag(repo, s, token).await;
+    }
+
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_next_link_finds_rel_next() {
+        let header = r#"<https://api.github.com/resource?page=2>; rel="next", <https://api.github.com/resource?page=5>; rel="last""#;
+        assert_eq!(
+            parse_next_link(header),
+            Some("https://api.github.com/resource?page=2".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_next_link_absent_without_rel_next() {
+        let header = r#"<https://api.github.com/resource?page=1>; rel="prev", <https://api.github.com/resource?page=5>; rel="last""#;
+        assert_eq!(parse_next_link(header), None);
+    }
+
+    #[test]
+    fn is_exact_pin_requires_full_version() {
+        assert!(is_exact_pin(&VersionReq::parse("=1.2.3").unwrap()));
+        assert!(!is_exact_pin(&VersionReq::parse("=1.2").unwrap()));
+        assert!(!is_exact_pin(&VersionReq::parse("=1").unwrap()));
+        assert!(!is_exact_pin(&VersionReq::parse("^1.2.3").unwrap()));
+    }
+
+    #[test]
+    fn req_wants_prerelease_checks_comparators() {
+        assert!(req_wants_prerelease(&VersionReq::parse("=1.2.3-rc1").unwrap()));
+        assert!(!req_wants_prerelease(&VersionReq::parse("^1.2.3").unwrap()));
+    }
+
+    #[test]
+    fn select_best_skips_prerelease_by_default() {
+        let items = vec![json!({"tag_name": "v2.0.0"}), json!({"tag_name": "v2.1.0-rc1"})];
+        let req = Some(VersionReq::parse("^2").unwrap());
+        let (_, tag, _) = select_best(&items, &req, false).unwrap();
+        assert_eq!(tag, "v2.0.0");
+    }
+
+    #[test]
+    fn select_best_honors_allow_prerelease_under_a_constraint() {
+        let items = vec![json!({"tag_name": "v2.0.0"}), json!({"tag_name": "v2.1.0-rc1"})];
+        let req = Some(VersionReq::parse("^2").unwrap());
+        let (_, tag, _) = select_best(&items, &req, true).unwrap();
+        assert_eq!(tag, "v2.1.0-rc1");
+    }
+}
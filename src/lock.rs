@@ -0,0 +1,57 @@
+//! `mkdeb.lock`: records exactly what was resolved for each package so a
+//! later build (with `--locked`) rebuilds from the same tag, commit and
+//! tarball URL instead of re-resolving "latest matching" against the GitHub API, the
+//! same way `Cargo.lock`/`package-lock.json` pin a dependency tree.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The exact resolution recorded for one package once its tarball has been
+/// fetched and hashed, before its build steps run; the entry is saved even
+/// if the build then fails.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub tag: String,
+    /// The commit `tag` pointed to when this was locked. Kept as a
+    /// fallback identity check: GitHub's auto-generated tarball archive
+    /// (`tarball_url`) isn't byte-stable over time (GitHub has changed its
+    /// archive compression before), so `integrity` below can drift even
+    /// when the source hasn't. `build_one` falls back to checking that the
+    /// extracted archive's directory name matches this commit instead of
+    /// aborting on an `integrity` mismatch for a lock-derived (not
+    /// explicitly pinned) pull.
+    pub commit: String,
+    pub tarball_url: String,
+    pub version: String,
+    /// Hash of the tarball at `tarball_url` when last resolved. Useful as
+    /// a first-line check and for the on-disk cache key, but not fully
+    /// authoritative — see `commit` above.
+    pub integrity: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub package: HashMap<String, LockedPackage>,
+}
+
+fn path() -> PathBuf {
+    PathBuf::from("mkdeb.lock")
+}
+
+/// Load `mkdeb.lock` from the current directory, or an empty lockfile if
+/// none exists yet (e.g. the first build of a fresh checkout).
+pub fn load() -> Lockfile {
+    fs::read_to_string(path())
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(lock: &Lockfile) -> io::Result<()> {
+    let content = toml::to_string_pretty(lock).expect("Failed to serialize lockfile");
+    fs::write(path(), content)
+}
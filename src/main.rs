@@ -1,6 +1,8 @@
+mod cache;
 mod cli;
 mod deb;
 mod github;
+mod lock;
 
 use crate::cli::CliArgs;
 use bytesize::ByteSize;
@@ -19,7 +21,12 @@ use std::{
     sync::Arc,
     time::Duration,
 };
-use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex, task::JoinHandle};
+use tokio::{
+    fs::File,
+    io::AsyncWriteExt,
+    sync::{Mutex, Semaphore},
+    task::JoinHandle,
+};
 
 #[derive(Debug, serde::Deserialize)]
 struct FilePackages {
@@ -49,7 +56,7 @@ pub fn load_all_configs(config_dir: &Path) -> Result<Vec<Package>, Box<dyn std::
     Ok(packages)
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct Package {
     #[serde(default)]
     pub name: String,
@@ -66,6 +73,61 @@ pub struct Package {
     pub maintainer: Option<String>,
     #[serde(default)]
     pub description: Option<String>,
+    /// Pin the exact bytes of the downloaded tarball, e.g.
+    /// `"sha512-9b71d224bd62f3785d96d46ad3ea3d73..."`. When set, a cached
+    /// blob satisfying it is used instead of hitting the network, and a
+    /// freshly downloaded tarball is rejected if it doesn't match.
+    ///
+    /// Sharp edge: this is an explicit pin, so a mismatch always aborts
+    /// the build — unlike the integrity mkdeb records in `mkdeb.lock`,
+    /// which falls back to a commit-SHA check instead (see
+    /// `lock::LockedPackage::integrity`) because GitHub's auto-generated
+    /// archive isn't byte-stable across time.
+    #[serde(default)]
+    pub integrity: Option<String>,
+    /// Accept releases whose version has a prerelease segment (`-rc1`,
+    /// `-beta`, ...) when resolving `version`. Off by default, matching
+    /// cargo's treatment of prereleases in version requirements.
+    #[serde(default)]
+    pub allow_prerelease: bool,
+    #[serde(default)]
+    pub preinst: Option<String>,
+    #[serde(default)]
+    pub postinst: Option<String>,
+    #[serde(default)]
+    pub prerm: Option<String>,
+    #[serde(default)]
+    pub postrm: Option<String>,
+    /// Paths (relative to the package root) that dpkg should preserve
+    /// across upgrades instead of overwriting with the new version.
+    #[serde(default)]
+    pub conffiles: Vec<String>,
+    #[serde(default)]
+    pub section: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub provides: Option<String>,
+    #[serde(default)]
+    pub conflicts: Option<String>,
+    #[serde(default)]
+    pub replaces: Option<String>,
+    /// Per-package override for the GitHub API token (see `--token`).
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Resolve the GitHub API token to use for `pkg`: an explicit `--token`
+/// flag wins, then the package's own `token` field, then the
+/// `GITHUB_TOKEN`/`MKDEB_GITHUB_TOKEN` environment variables.
+fn resolve_github_token(args: &CliArgs, pkg: &Package) -> Option<String> {
+    args.token
+        .clone()
+        .or_else(|| pkg.token.clone())
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .or_else(|| std::env::var("MKDEB_GITHUB_TOKEN").ok())
 }
 
 fn spawn_log_task<T: std::io::Read + Send + 'static>(
@@ -89,13 +151,17 @@ fn spawn_log_task<T: std::io::Read + Send + 'static>(
     })
 }
 
+/// Run `cmd_str` under `bash -c`, tee'ing stdout/stderr to the log file and
+/// (if `verbose`) the terminal. Returns `Err` with a description on a
+/// non-zero exit instead of killing the process, so one package's failing
+/// build doesn't take down sibling builds running concurrently.
 async fn run_command(
     cmd_str: &str,
     cwd: &PathBuf,
     verbose: u8,
     destdir: Option<&str>,
     log_file_path: Option<&Path>,
-) {
+) -> Result<(), String> {
     let interpolated = if let Some(dest) = destdir {
         cmd_str.replace("{destdir}", dest)
     } else {
@@ -119,11 +185,12 @@ async fn run_command(
     let stdout = child.stdout.take().expect("Failed to capture stdout");
     let stderr = child.stderr.take().expect("Failed to capture stderr");
 
-    let log_file: Option<std::fs::File> = match log_file_path {
-        Some(path) => Some(std::fs::File::create(path).unwrap_or_else(|e| {
-            eprintln!("Failed to create log file {:?}: {}", path, e);
-            std::process::exit(1);
-        })),
+    let log_file = match log_file_path {
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .map_err(|e| format!("Failed to create log file {:?}: {}", path, e))?;
+            Some(file)
+        }
         None => None,
     };
 
@@ -138,15 +205,23 @@ async fn run_command(
     handle_stderr.await.unwrap();
 
     if !status.success() {
-        eprintln!("Command failed: {:?}", cmd);
-        std::process::exit(1);
+        return Err(format!("Command failed: {:?}", cmd));
     }
+
+    Ok(())
 }
 
+/// Download `url` to `dest`, hashing the bytes as they're streamed.
+///
+/// `expect_algo` picks the hash algorithm to use, taken from the prefix of
+/// an expected integrity string when the caller has one (so the computed
+/// digest is directly comparable), or "sha512" otherwise. Returns the
+/// resulting `sha512-<base64>`/`sha256-<base64>` integrity string.
 pub async fn download_with_progress(
     url: &str,
     dest: &Path,
-) -> Result<(), Box<dyn std::error::Error>> {
+    expect_algo: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
     let client = reqwest::Client::new();
     let response = client
         .get(url)
@@ -183,10 +258,12 @@ pub async fn download_with_progress(
 
     let mut file = File::create(dest).await?;
     let mut stream = response.bytes_stream();
+    let mut hasher = cache::StreamingHasher::new(expect_algo);
 
     let mut downloaded = 0u64;
     while let Some(chunk) = stream.next().await {
         let chunk = chunk?;
+        hasher.update(&chunk);
         file.write_all(&chunk).await?;
         downloaded += chunk.len() as u64;
         if let Some(pb) = &pb {
@@ -199,7 +276,373 @@ pub async fn download_with_progress(
         pb.finish_with_message("Download complete");
     }
 
-    Ok(())
+    Ok(hasher.finish())
+}
+
+/// Outcome of building a single package, collected into the final summary
+/// table instead of being printed (and possibly aborting the run) inline.
+enum BuildOutcome {
+    Success,
+    Skipped,
+    Failed(String),
+}
+
+struct BuildResult {
+    name: String,
+    version: String,
+    outcome: BuildOutcome,
+}
+
+/// Resolve, download, configure/build/install, and package one `pkg`.
+/// Runs as its own tokio task so a failure here doesn't abort sibling
+/// packages being built concurrently. Resolve, download, integrity and
+/// configure/build/install failures report back through the returned
+/// `BuildResult`; unpacking, `dpkg-deb` and `dpkg -i` failures panic the
+/// task, which `main` reports as a failed row named `?`. Neither exits
+/// the process.
+async fn build_one(
+    pkg: Package,
+    args: Arc<CliArgs>,
+    architecture: Arc<String>,
+    lockfile: Arc<Mutex<lock::Lockfile>>,
+) -> BuildResult {
+    let locked_entry = {
+        let lock = lockfile.lock().await;
+        lock.package.get(&pkg.name).cloned()
+    };
+
+    let release = if args.locked && !args.update {
+        match &locked_entry {
+            Some(entry) => github::GithubRelease {
+                tag: entry.tag.clone(),
+                tarball_url: entry.tarball_url.clone(),
+                version: entry.version.clone(),
+                commit: entry.commit.clone(),
+            },
+            None => {
+                return BuildResult {
+                    name: pkg.name.clone(),
+                    version: "?".to_string(),
+                    outcome: BuildOutcome::Failed(format!(
+                        "no mkdeb.lock entry for {} (run with --update)",
+                        pkg.name
+                    )),
+                };
+            }
+        }
+    } else {
+        let token = resolve_github_token(&args, &pkg);
+        match github::find_release(
+            &pkg.repo,
+            pkg.version.as_deref(),
+            pkg.allow_prerelease,
+            token.as_deref(),
+        )
+        .await
+        {
+            Some(r) => r,
+            None => {
+                return BuildResult {
+                    name: pkg.name.clone(),
+                    version: "?".to_string(),
+                    outcome: BuildOutcome::Failed(format!("could not find release for {}", pkg.repo)),
+                };
+            }
+        }
+    };
+    let version = release.version;
+    let repo_url = release.tarball_url;
+    let tag = release.tag;
+    let commit = release.commit;
+
+    if args.install {
+        if let Some(installed_ver) = deb::get_installed_version(&pkg.name) {
+            if installed_ver == version {
+                info!("{} {} already installed.", pkg.name, version);
+                return BuildResult {
+                    name: pkg.name,
+                    version,
+                    outcome: BuildOutcome::Skipped,
+                };
+            }
+        }
+    }
+
+    info!(
+        "Building {} version {} tag {} url {}",
+        pkg.name, version, tag, repo_url
+    );
+
+    macro_rules! fail {
+        ($($arg:tt)*) => {
+            return BuildResult {
+                name: pkg.name.clone(),
+                version: version.clone(),
+                outcome: BuildOutcome::Failed(format!($($arg)*)),
+            }
+        };
+    }
+
+    let work_dir = if let Some(ref path) = args.build_root {
+        let path = PathBuf::from(path).join(format!("{}-{}", pkg.name, version));
+        if let Err(e) = fs::create_dir_all(&path) {
+            fail!("failed to create build root: {}", e);
+        }
+        path
+    } else {
+        match tempfile::tempdir() {
+            Ok(dir) => dir.into_path(),
+            Err(e) => fail!("failed to create temporary directory: {}", e),
+        }
+    };
+    let src_tar = work_dir.join("src.tar.gz");
+
+    // A pin can come from the package config directly, or (short of that)
+    // from the lockfile entry, but only when `release` itself came from the
+    // lockfile (`--locked` without `--update`). Once we've re-resolved
+    // against the GitHub API, the lockfile's hash belongs to whatever was
+    // locked before and would reject a legitimately newer tarball.
+    let expected_integrity = pkg.integrity.clone().or_else(|| {
+        if args.locked && !args.update {
+            locked_entry.as_ref().map(|e| e.integrity.clone())
+        } else {
+            None
+        }
+    });
+
+    let cached = if args.no_cache {
+        None
+    } else {
+        expected_integrity.as_deref().and_then(cache::lookup)
+    };
+
+    // Set when the tarball hash didn't match a *lock-derived* pin (not an
+    // explicit `integrity = "..."` in the package config): GitHub's
+    // auto-generated tarball_url archive isn't byte-stable (GitHub has
+    // changed its archive compression before), so this can fire on an
+    // unchanged source. We fall back to checking the extracted directory
+    // name against the locked commit SHA instead of aborting outright.
+    let mut verify_commit_instead = false;
+
+    let integrity = if let Some(cached_path) = cached {
+        let expected = expected_integrity.clone().unwrap();
+        info!("Using cached tarball for {} ({})", pkg.name, expected);
+        if let Err(e) = fs::copy(&cached_path, &src_tar) {
+            fail!("failed to copy cached tarball: {}", e);
+        }
+        expected
+    } else {
+        debug!("Downloading {}", repo_url);
+        let expect_algo = expected_integrity
+            .as_deref()
+            .map(cache::algo_of)
+            .unwrap_or("sha512");
+        let integrity = match download_with_progress(&repo_url, &src_tar, expect_algo).await {
+            Ok(i) => i,
+            Err(e) => fail!("download failed: {}", e),
+        };
+
+        if let Some(expected) = expected_integrity.as_deref() {
+            if integrity != expected {
+                if pkg.integrity.is_some() {
+                    fail!(
+                        "integrity check failed: expected {}, got {}",
+                        expected,
+                        integrity
+                    );
+                }
+                error!(
+                    "{}: tarball hash changed ({} -> {}); GitHub regenerates archives from \
+                     time to time, so this doesn't necessarily mean the source moved — \
+                     verifying against the locked commit {} instead",
+                    pkg.name, expected, integrity, commit
+                );
+                verify_commit_instead = true;
+            }
+        }
+
+        if !args.no_cache {
+            if let Err(e) = cache::store(&integrity, &src_tar) {
+                error!("Failed to populate cache for {}: {}", pkg.name, e);
+            }
+        }
+
+        integrity
+    };
+
+    if !args.locked || args.update {
+        let mut lock = lockfile.lock().await;
+        lock.package.insert(
+            pkg.name.clone(),
+            lock::LockedPackage {
+                tag: tag.clone(),
+                commit: commit.clone(),
+                tarball_url: repo_url.clone(),
+                version: version.clone(),
+                integrity: integrity.clone(),
+            },
+        );
+    }
+
+    let tar = fs::File::open(&src_tar).unwrap();
+    let gz = flate2::read::GzDecoder::new(tar);
+    let mut archive = tar::Archive::new(gz);
+    archive.unpack(&work_dir).unwrap();
+
+    let extracted_dir = fs::read_dir(work_dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_type().unwrap().is_dir())
+        .unwrap()
+        .path();
+
+    if verify_commit_instead {
+        // GitHub's tarball extracts to a `{owner}-{repo}-{short_sha}`
+        // directory; that's the one commit-identifying signal left once
+        // the tarball hash itself can't be trusted.
+        let dir_name = extracted_dir.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let short_sha = &commit[..commit.len().min(7)];
+        if commit == "unknown" || !dir_name.ends_with(short_sha) {
+            fail!(
+                "tarball hash changed and the extracted archive ({}) doesn't match the locked commit {}; \
+                 refusing to build what may be different source",
+                dir_name, commit
+            );
+        }
+        info!("{}: verified extracted archive matches locked commit {}", pkg.name, commit);
+    }
+
+    let destdir = extracted_dir.canonicalize().unwrap().join("pkg");
+    debug!("destdir is {:#?}", destdir);
+
+    let debian_dir = destdir.join("DEBIAN");
+    fs::create_dir_all(&debian_dir).unwrap();
+
+    let mut extra_fields = Vec::new();
+    if let Some(v) = pkg.section.as_deref() {
+        extra_fields.push(("Section", v));
+    }
+    if let Some(v) = pkg.priority.as_deref() {
+        extra_fields.push(("Priority", v));
+    }
+    if let Some(v) = pkg.homepage.as_deref() {
+        extra_fields.push(("Homepage", v));
+    }
+    if let Some(v) = pkg.provides.as_deref() {
+        extra_fields.push(("Provides", v));
+    }
+    if let Some(v) = pkg.conflicts.as_deref() {
+        extra_fields.push(("Conflicts", v));
+    }
+    if let Some(v) = pkg.replaces.as_deref() {
+        extra_fields.push(("Replaces", v));
+    }
+
+    deb::write_control(
+        &deb::ControlMetadata {
+            name: &pkg.name,
+            version: &version,
+            arch: &architecture,
+            maintainer: pkg.maintainer.as_deref(),
+            description: pkg.description.as_deref(),
+            deps: pkg.deps.as_deref(),
+            build_deps: pkg.build_deps.as_deref(),
+            extra_fields,
+        },
+        &debian_dir,
+    );
+
+    deb::write_maintainer_scripts(
+        &deb::MaintainerScripts {
+            preinst: pkg.preinst.as_deref(),
+            postinst: pkg.postinst.as_deref(),
+            prerm: pkg.prerm.as_deref(),
+            postrm: pkg.postrm.as_deref(),
+        },
+        &debian_dir,
+    );
+
+    deb::write_conffiles(&pkg.conffiles, &debian_dir);
+
+    let log_dir = args
+        .log_dir
+        .clone()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("./logs"));
+    fs::create_dir_all(&log_dir).ok();
+
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S");
+    let configure_log = if args.log {
+        Some(log_dir.join(format!("{}-configure-{}.log", pkg.name, timestamp)))
+    } else {
+        None
+    };
+    let build_log = if args.log {
+        Some(log_dir.join(format!("{}-build-{}.log", pkg.name, timestamp)))
+    } else {
+        None
+    };
+    let install_log = if args.log {
+        Some(log_dir.join(format!("{}-install-{}.log", pkg.name, timestamp)))
+    } else {
+        None
+    };
+
+    if let Some(cfg) = &pkg.configure {
+        if let Err(e) = run_command(
+            cfg,
+            &extracted_dir,
+            args.verbose,
+            Some(destdir.to_str().unwrap()),
+            configure_log.as_deref(),
+        )
+        .await
+        {
+            fail!("configure failed: {}", e);
+        }
+    }
+
+    if let Some(bld) = &pkg.build {
+        if let Err(e) = run_command(
+            bld,
+            &extracted_dir,
+            args.verbose,
+            Some(destdir.to_str().unwrap()),
+            build_log.as_deref(),
+        )
+        .await
+        {
+            fail!("build failed: {}", e);
+        }
+    }
+
+    if let Some(install_cmd) = &pkg.install {
+        if let Err(e) = run_command(
+            install_cmd,
+            &extracted_dir,
+            args.verbose,
+            Some(destdir.to_str().unwrap()),
+            install_log.as_deref(),
+        )
+        .await
+        {
+            fail!("install failed: {}", e);
+        }
+    }
+
+    let deb_name = format!("{}-{}.deb", pkg.name, version);
+    let output_path = PathBuf::from(&deb_name);
+    deb::build_package(&destdir, &output_path);
+
+    if args.install {
+        deb::install_package(&output_path);
+    }
+
+    BuildResult {
+        name: pkg.name,
+        version,
+        outcome: BuildOutcome::Success,
+    }
 }
 
 #[tokio::main]
@@ -243,6 +686,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         exit(1);
     };
 
+    let lockfile = lock::load();
+
     if args.list {
         let mut table = Table::new();
         table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
@@ -255,10 +700,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         for pkg in selected {
             let installed = deb::get_installed_version(&pkg.name).unwrap_or_else(|| "none".into());
 
-            let version = github::find_release(&pkg.repo, pkg.version.as_deref())
+            // Under --locked, list is pinned to mkdeb.lock instead of
+            // hitting the network, so it stays offline and deterministic.
+            let version = if args.locked {
+                lockfile
+                    .package
+                    .get(&pkg.name)
+                    .map(|e| e.version.clone())
+                    .unwrap_or_else(|| "(not locked)".to_string())
+            } else {
+                let token = resolve_github_token(&args, pkg);
+                github::find_release(
+                    &pkg.repo,
+                    pkg.version.as_deref(),
+                    pkg.allow_prerelease,
+                    token.as_deref(),
+                )
                 .await
                 .map(|r| r.version)
-                .unwrap_or_else(|| "(not found)".to_string());
+                .unwrap_or_else(|| "(not found)".to_string())
+            };
 
             table.add_row(Row::new(vec![
                 Cell::new(&pkg.name),
@@ -270,136 +731,75 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    for pkg in selected {
-        let release = github::find_release(&pkg.repo, pkg.version.as_deref())
-            .await
-            .unwrap_or_else(|| {
-                eprintln!("Could not find release for {}", pkg.repo);
-                std::process::exit(1);
-            });
-        let version = release.version;
-        let repo_url = release.tarball_url;
-
-        if args.install {
-            if let Some(installed_ver) = deb::get_installed_version(&pkg.name) {
-                if installed_ver == version {
-                    info!("{} {} already installed.", pkg.name, version);
-                    return Ok(());
-                }
-            }
-        }
-
-        info!(
-            "Building {} version {} tag {} url {}",
-            pkg.name, version, release.tag, repo_url
-        );
-
-        let work_dir = if let Some(ref path) = args.build_root {
-            let path = PathBuf::from(path).join(format!("{}-{}", pkg.name, version));
-            fs::create_dir_all(&path).expect("Failed to create build root");
-            path
-        } else {
-            tempfile::tempdir().unwrap().into_path()
-        };
-        let src_tar = work_dir.join("src.tar.gz");
-
-        debug!("Downloading {}", repo_url);
-        download_with_progress(&repo_url, &src_tar).await?;
-
-        let tar = fs::File::open(&src_tar).unwrap();
-        let gz = flate2::read::GzDecoder::new(tar);
-        let mut archive = tar::Archive::new(gz);
-        archive.unpack(&work_dir).unwrap();
-
-        let extracted_dir = fs::read_dir(work_dir)
-            .unwrap()
-            .filter_map(|e| e.ok())
-            .find(|e| e.file_type().unwrap().is_dir())
-            .unwrap()
-            .path();
-
-        let destdir = extracted_dir.canonicalize().unwrap().join("pkg");
-        debug!("destdir is {:#?}", destdir);
-
-        fs::create_dir_all(destdir.join("DEBIAN")).unwrap();
-
-        deb::write_control(
-            &deb::ControlMetadata {
-                name: &pkg.name,
-                version: &version,
-                arch: &architecture,
-                maintainer: pkg.maintainer.as_deref(),
-                description: pkg.description.as_deref(),
-                deps: pkg.deps.as_deref(),
-                build_deps: pkg.build_deps.as_deref(),
-            },
-            &destdir.join("DEBIAN"),
-        );
-
-        let log_dir = args
-            .log_dir
-            .clone()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("./logs"));
-        fs::create_dir_all(&log_dir).ok();
-
-        let timestamp = Local::now().format("%Y%m%d-%H%M%S");
-        let configure_log = if args.log {
-            Some(log_dir.join(format!("{}-configure-{}.log", pkg.name, timestamp)))
-        } else {
-            None
-        };
-        let build_log = if args.log {
-            Some(log_dir.join(format!("{}-build-{}.log", pkg.name, timestamp)))
-        } else {
-            None
-        };
-        let install_log = if args.log {
-            Some(log_dir.join(format!("{}-install-{}.log", pkg.name, timestamp)))
-        } else {
-            None
-        };
-
-        if let Some(cfg) = &pkg.configure {
-            run_command(
-                cfg,
-                &extracted_dir,
-                args.verbose,
-                Some(destdir.to_str().unwrap()),
-                configure_log.as_deref(),
-            )
-            .await;
-        }
+    let jobs = args.jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    info!("Building {} package(s) with {} job(s)", selected.len(), jobs);
+
+    let semaphore = Arc::new(Semaphore::new(jobs));
+    let architecture = Arc::new(architecture);
+    let lockfile = Arc::new(Mutex::new(lockfile));
+    let args = Arc::new(args);
+
+    let mut handles = Vec::new();
+    for pkg in selected.into_iter().cloned() {
+        let semaphore = semaphore.clone();
+        let args = args.clone();
+        let architecture = architecture.clone();
+        let lockfile = lockfile.clone();
+        handles.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await.unwrap();
+            build_one(pkg, args, architecture, lockfile).await
+        }));
+    }
 
-        if let Some(bld) = &pkg.build {
-            run_command(
-                bld,
-                &extracted_dir,
-                args.verbose,
-                Some(destdir.to_str().unwrap()),
-                build_log.as_deref(),
-            )
-            .await;
+    let mut results = Vec::new();
+    for handle in handles {
+        match handle.await {
+            Ok(result) => results.push(result),
+            Err(e) => results.push(BuildResult {
+                name: "?".to_string(),
+                version: "?".to_string(),
+                outcome: BuildOutcome::Failed(format!("build task panicked: {}", e)),
+            }),
         }
+    }
 
-        if let Some(install_cmd) = &pkg.install {
-            run_command(
-                install_cmd,
-                &extracted_dir,
-                args.verbose,
-                Some(destdir.to_str().unwrap()),
-                install_log.as_deref(),
-            )
-            .await;
-        }
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.add_row(Row::new(vec![
+        Cell::new("Package").style_spec("Fb"),
+        Cell::new("Version").style_spec("Fb"),
+        Cell::new("Result").style_spec("Fb"),
+    ]));
+
+    let mut failed = false;
+    for result in &results {
+        let status_cell = match &result.outcome {
+            BuildOutcome::Success => Cell::new("ok").style_spec("Fg"),
+            BuildOutcome::Skipped => Cell::new("skipped").style_spec("Fy"),
+            BuildOutcome::Failed(detail) => {
+                failed = true;
+                Cell::new(&format!("failed ({})", detail)).style_spec("Fr")
+            }
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&result.name),
+            Cell::new(&result.version),
+            status_cell,
+        ]));
+    }
+    table.printstd();
 
-        let deb_name = format!("{}-{}.deb", pkg.name, version);
-        let output_path = PathBuf::from(&deb_name);
-        deb::build_package(&destdir, &output_path);
+    if let Err(e) = lock::save(&*lockfile.lock().await) {
+        error!("Failed to write mkdeb.lock: {}", e);
+    }
 
-        if args.install {
-            deb::install_package(&output_path);
-        }
+    if failed {
+        exit(1);
     }
+
     Ok(())
 }